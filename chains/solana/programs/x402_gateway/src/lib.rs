@@ -6,9 +6,9 @@ use solana_program::{
     clock::Clock,
     entrypoint,
     entrypoint::ProgramResult,
-    instruction::Instruction,
+    instruction::{AccountMeta, Instruction},
     msg,
-    program::invoke,
+    program::{invoke, invoke_signed},
     program_error::ProgramError,
     pubkey::Pubkey,
     rent::Rent,
@@ -18,12 +18,15 @@ use solana_system_interface::instruction as system_instruction;
 
 solana_program::declare_id!("6F2rv4dbwJ7A3F9Q8NpL6X2kYQ6Zxj2Y8ywmupfHP2aG");
 
-pub const STATE_SIZE: usize = 8 + 32 + 32 + 32;
+pub const STATE_SIZE: usize = 8 + 32 + 32 + 32 + 1;
 pub const STATE_DISCRIMINATOR: [u8; 8] = [0x78, 0x34, 0x30, 0x32, 0x5f, 0x73, 0x6d, 0x74];
 pub const PROOF_LEN: usize = 388;
 pub const WITNESS_LEN: usize = 76;
 pub const PAY_AUTHORIZED_HEADER_LEN: usize = 32 + 8 + 8; // auth_id + amount + auth_expiry
 pub const PAY_AUTHORIZED_DATA_LEN: usize = PAY_AUTHORIZED_HEADER_LEN + PROOF_LEN + WITNESS_LEN;
+/// Size of the PDA that marks an `auth_id` as spent; it only needs to exist,
+/// so it carries no data.
+pub const SPENT_MARKER_SIZE: usize = 0;
 
 pub mod instruction {
     pub const INITIALIZE_STATE: u8 = 0;
@@ -40,6 +43,9 @@ pub enum GatewayError {
     InvalidStatePda = 3,
     InvalidZkVerifier = 4,
     AuthorizationExpired = 5,
+    AuthorizationAlreadySpent = 6,
+    InvalidSpentAuthorizationPda = 7,
+    InvalidAccountAliasing = 8,
 }
 
 impl From<GatewayError> for ProgramError {
@@ -48,6 +54,99 @@ impl From<GatewayError> for ProgramError {
     }
 }
 
+/// On-chain layout of the gateway's state account: an 8-byte discriminator
+/// followed by the admin authority, the current SMT root, the configured
+/// zk-verifier program, and the PDA bump seed for `[b"state", admin]`.
+/// `#[repr(C)]` and all byte-array fields give it a fixed, alignment-1
+/// layout so it can be cast directly over the account's data slice;
+/// off-chain clients can mirror this struct to decode the account without
+/// going through the program.
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct GatewayState {
+    pub discriminator: [u8; 8],
+    pub admin: Pubkey,
+    pub smt_root: [u8; 32],
+    pub zk_verifier: Pubkey,
+    pub bump: u8,
+}
+
+impl GatewayState {
+    pub const LEN: usize = STATE_SIZE;
+}
+
+/// Casts `data` onto `GatewayState` without checking the discriminator,
+/// for use while initializing a freshly created, still-zeroed account.
+fn state_mut_unchecked(data: &mut [u8]) -> Result<&mut GatewayState, GatewayError> {
+    if data.len() < GatewayState::LEN {
+        return Err(GatewayError::InvalidStateAccount);
+    }
+    Ok(unsafe { &mut *(data.as_mut_ptr() as *mut GatewayState) })
+}
+
+/// Validates `data` is long enough and carries `STATE_DISCRIMINATOR`, then
+/// returns it as a typed `GatewayState` reference.
+pub fn get_state(data: &[u8]) -> Result<&GatewayState, GatewayError> {
+    if data.len() < GatewayState::LEN {
+        return Err(GatewayError::InvalidStateAccount);
+    }
+    let state = unsafe { &*(data.as_ptr() as *const GatewayState) };
+    if state.discriminator != STATE_DISCRIMINATOR {
+        return Err(GatewayError::InvalidStateAccount);
+    }
+    Ok(state)
+}
+
+/// Mutable counterpart of [`get_state`].
+pub fn get_state_mut(data: &mut [u8]) -> Result<&mut GatewayState, GatewayError> {
+    let state = state_mut_unchecked(data)?;
+    if state.discriminator != STATE_DISCRIMINATOR {
+        return Err(GatewayError::InvalidStateAccount);
+    }
+    Ok(state)
+}
+
+/// Builds the `AccountMeta` list forwarded to the zk-verifier CPI from the
+/// trailing accounts a caller attaches to `PayAuthorized`, preserving each
+/// account's signer/writable flags.
+fn verifier_account_metas(accounts: &[&AccountInfo]) -> Vec<AccountMeta> {
+    accounts
+        .iter()
+        .map(|info| AccountMeta {
+            pubkey: *info.key,
+            is_signer: info.is_signer,
+            is_writable: info.is_writable,
+        })
+        .collect()
+}
+
+/// Builds the full account metas and `AccountInfo`s for the zk-verifier
+/// CPI in `process_pay_authorized`: the state PDA (signed via
+/// `invoke_signed`'s seeds so verifiers can gate on the gateway's
+/// authority), the caller-supplied trailing accounts, and finally the
+/// verifier program's own `AccountInfo`, matching this file's convention
+/// of appending the invoked program last (see `process_initialize`).
+fn build_verifier_cpi<'a>(
+    state_account: &AccountInfo<'a>,
+    zk_verifier: &AccountInfo<'a>,
+    verifier_accounts: &[&AccountInfo<'a>],
+) -> (Vec<AccountMeta>, Vec<AccountInfo<'a>>) {
+    let mut metas = Vec::with_capacity(verifier_accounts.len() + 1);
+    metas.push(AccountMeta {
+        pubkey: *state_account.key,
+        is_signer: true,
+        is_writable: state_account.is_writable,
+    });
+    metas.extend(verifier_account_metas(verifier_accounts));
+
+    let mut infos = Vec::with_capacity(verifier_accounts.len() + 2);
+    infos.push(state_account.clone());
+    infos.extend(verifier_accounts.iter().map(|info| (*info).clone()));
+    infos.push(zk_verifier.clone());
+
+    (metas, infos)
+}
+
 entrypoint!(process_instruction);
 
 pub fn process_instruction(
@@ -101,7 +200,7 @@ fn process_initialize(
     let lamports = rent.minimum_balance(STATE_SIZE);
     let signer_seeds: &[&[u8]] = &[b"state", admin.key.as_ref(), &[bump]];
 
-    solana_program::program::invoke_signed(
+    invoke_signed(
         &system_instruction::create_account(
             admin.key,
             state_account.key,
@@ -114,10 +213,16 @@ fn process_initialize(
     )?;
 
     let mut state_data = state_account.try_borrow_mut_data()?;
-    state_data[0..8].copy_from_slice(&STATE_DISCRIMINATOR);
-    state_data[8..40].copy_from_slice(admin.key.as_ref());
-    state_data[40..72].copy_from_slice(&[0u8; 32]); // smt_root
-    state_data[72..104].copy_from_slice(init_data);
+    let state = state_mut_unchecked(&mut state_data)?;
+    state.discriminator = STATE_DISCRIMINATOR;
+    state.admin = *admin.key;
+    state.smt_root = [0u8; 32];
+    state.zk_verifier = Pubkey::new_from_array(
+        init_data
+            .try_into()
+            .map_err(|_| GatewayError::InvalidDataLength)?,
+    );
+    state.bump = bump;
 
     Ok(())
 }
@@ -146,16 +251,13 @@ fn process_set_smt_root(
     }
 
     let mut state_data = state_account.try_borrow_mut_data()?;
-    if state_data[0..8] != STATE_DISCRIMINATOR {
-        return Err(GatewayError::InvalidStateAccount.into());
-    }
-
-    state_data[40..72].copy_from_slice(data);
+    let state = get_state_mut(&mut state_data)?;
+    state.smt_root.copy_from_slice(data);
     Ok(())
 }
 
 fn process_pay_authorized(
-    _program_id: &Pubkey,
+    program_id: &Pubkey,
     accounts: &[AccountInfo],
     data: &[u8],
 ) -> ProgramResult {
@@ -170,22 +272,63 @@ fn process_pay_authorized(
     let state_account = next_account_info(account_iter)?;
     let zk_verifier = next_account_info(account_iter)?;
     let system_program = next_account_info(account_iter)?;
+    let spent_authorization = next_account_info(account_iter)?;
+    let verifier_accounts: Vec<&AccountInfo> = account_iter.collect();
 
     if !payer.is_signer {
         return Err(ProgramError::MissingRequiredSignature);
     }
 
-    let state_data = state_account.try_borrow_data()?;
-    if state_data[0..8] != STATE_DISCRIMINATOR {
-        return Err(GatewayError::InvalidStateAccount.into());
+    if payer.key == recipient.key {
+        return Err(GatewayError::InvalidAccountAliasing.into());
+    }
+    if state_account.owner != program_id
+        || state_account.key == payer.key
+        || state_account.key == recipient.key
+    {
+        return Err(GatewayError::InvalidAccountAliasing.into());
+    }
+    if zk_verifier.key == system_program.key {
+        return Err(GatewayError::InvalidAccountAliasing.into());
     }
-    let configured_verifier = Pubkey::new_from_array(state_data[72..104].try_into().unwrap());
-    if zk_verifier.key != &configured_verifier {
+
+    let state_data = state_account.try_borrow_data()?;
+    let state = get_state(&state_data)?;
+    if zk_verifier.key != &state.zk_verifier {
         return Err(GatewayError::InvalidZkVerifier.into());
     }
+    let configured_verifier = state.zk_verifier;
+    let stored_smt_root = state.smt_root;
+    let state_admin = state.admin;
+    let state_bump = state.bump;
+    drop(state_data);
+
+    let auth_id: [u8; 32] = data
+        .get(0..32)
+        .ok_or(GatewayError::InvalidDataLength)?
+        .try_into()
+        .map_err(|_| GatewayError::InvalidDataLength)?;
+
+    let (spent_pda, spent_bump) = Pubkey::find_program_address(&[b"spent", &auth_id], program_id);
+    if spent_authorization.key != &spent_pda {
+        return Err(GatewayError::InvalidSpentAuthorizationPda.into());
+    }
+    if spent_authorization.owner == program_id {
+        return Err(GatewayError::AuthorizationAlreadySpent.into());
+    }
 
-    let amount = u64::from_le_bytes(data[32..40].try_into().unwrap());
-    let auth_expiry = u64::from_le_bytes(data[40..48].try_into().unwrap());
+    let amount = u64::from_le_bytes(
+        data.get(32..40)
+            .ok_or(GatewayError::InvalidDataLength)?
+            .try_into()
+            .map_err(|_| GatewayError::InvalidDataLength)?,
+    );
+    let auth_expiry = u64::from_le_bytes(
+        data.get(40..48)
+            .ok_or(GatewayError::InvalidDataLength)?
+            .try_into()
+            .map_err(|_| GatewayError::InvalidDataLength)?,
+    );
 
     let now = Clock::get()?.unix_timestamp;
     if now > auth_expiry as i64 {
@@ -193,31 +336,65 @@ fn process_pay_authorized(
     }
 
     let proof_start = PAY_AUTHORIZED_HEADER_LEN;
-    let proof_end = proof_start + PROOF_LEN;
-    let witness_data = &data[proof_end..proof_end + WITNESS_LEN];
-
-    let stored_smt_root = &state_data[40..72];
-    let witness_smt_root = &witness_data[12..44];
-    if witness_smt_root != stored_smt_root {
+    let proof_end = proof_start
+        .checked_add(PROOF_LEN)
+        .ok_or(GatewayError::InvalidDataLength)?;
+    let witness_end = proof_end
+        .checked_add(WITNESS_LEN)
+        .ok_or(GatewayError::InvalidDataLength)?;
+    let witness_data = data
+        .get(proof_end..witness_end)
+        .ok_or(GatewayError::InvalidDataLength)?;
+
+    let witness_smt_root = witness_data
+        .get(12..44)
+        .ok_or(GatewayError::InvalidDataLength)?;
+    if witness_smt_root != stored_smt_root.as_slice() {
         return Err(GatewayError::SmtRootMismatch.into());
     }
 
+    let proof_data = data
+        .get(proof_start..proof_end)
+        .ok_or(GatewayError::InvalidDataLength)?;
     let mut verifier_data = Vec::with_capacity(PROOF_LEN + WITNESS_LEN);
-    verifier_data.extend_from_slice(&data[proof_start..proof_end]);
+    verifier_data.extend_from_slice(proof_data);
     verifier_data.extend_from_slice(witness_data);
 
+    let (verifier_metas, verifier_infos) =
+        build_verifier_cpi(state_account, zk_verifier, &verifier_accounts);
+
     let verify_ix = Instruction {
         program_id: configured_verifier,
-        accounts: vec![],
+        accounts: verifier_metas,
         data: verifier_data,
     };
-    invoke(&verify_ix, &[])?;
+    let signer_seeds: &[&[u8]] = &[b"state", state_admin.as_ref(), &[state_bump]];
+    invoke_signed(&verify_ix, &verifier_infos, &[signer_seeds])?;
 
     msg!(
         "x402 PayAuthorized verified, auth_id_prefix={:?}",
-        &data[0..4]
+        data.get(0..4).ok_or(GatewayError::InvalidDataLength)?
     );
 
+    let rent = Rent::get()?;
+    let lamports = rent.minimum_balance(SPENT_MARKER_SIZE);
+    let spent_signer_seeds: &[&[u8]] = &[b"spent", &auth_id, &[spent_bump]];
+    invoke_signed(
+        &system_instruction::create_account(
+            payer.key,
+            spent_authorization.key,
+            lamports,
+            SPENT_MARKER_SIZE as u64,
+            program_id,
+        ),
+        &[
+            payer.clone(),
+            spent_authorization.clone(),
+            system_program.clone(),
+        ],
+        &[spent_signer_seeds],
+    )?;
+
     invoke(
         &system_instruction::transfer(payer.key, recipient.key, amount),
         &[payer.clone(), recipient.clone(), system_program.clone()],
@@ -362,9 +539,10 @@ mod tests {
         let state = new_account(Pubkey::new_unique(), program_id, false, false, STATE_SIZE);
         let verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
         let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
         let err = process_pay_authorized(
             &program_id,
-            &[payer, recipient, state, verifier, system],
+            &[payer, recipient, state, verifier, system, spent],
             &empty_pay_authorized_data(),
         )
         .unwrap_err();
@@ -379,9 +557,18 @@ mod tests {
         let state = new_account(Pubkey::new_unique(), program_id, false, false, STATE_SIZE);
         let verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
         let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+
+        {
+            let mut state_data = state.try_borrow_mut_data().unwrap();
+            let gateway_state = state_mut_unchecked(&mut state_data).unwrap();
+            gateway_state.discriminator = STATE_DISCRIMINATOR;
+            gateway_state.zk_verifier = Pubkey::new_unique();
+        }
+
         let err = process_pay_authorized(
             &program_id,
-            &[payer, recipient, state, verifier, system],
+            &[payer, recipient, state, verifier, system, spent],
             &empty_pay_authorized_data(),
         )
         .unwrap_err();
@@ -390,4 +577,266 @@ mod tests {
             ProgramError::Custom(GatewayError::InvalidZkVerifier as u32)
         );
     }
+
+    #[test]
+    fn set_root_rejects_undersized_state_account() {
+        let program_id = Pubkey::new_unique();
+        let admin_key = Pubkey::new_unique();
+        let admin = new_account(admin_key, program_id, true, true, 0);
+        let (state_pda, _bump) =
+            Pubkey::find_program_address(&[b"state", admin_key.as_ref()], &program_id);
+        let state = new_account(state_pda, program_id, false, true, STATE_SIZE - 1);
+
+        let err = process_set_smt_root(&program_id, &[admin, state], &[0u8; 32]).unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidStateAccount as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_undersized_state_account() {
+        let program_id = Pubkey::new_unique();
+        let payer = new_account(Pubkey::new_unique(), program_id, true, true, 0);
+        let recipient = new_account(Pubkey::new_unique(), program_id, false, true, 0);
+        let state = new_account(Pubkey::new_unique(), program_id, false, false, STATE_SIZE - 1);
+        let verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &empty_pay_authorized_data(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidStateAccount as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_invalid_spent_authorization_pda() {
+        let program_id = Pubkey::new_unique();
+        let payer = new_account(Pubkey::new_unique(), program_id, true, true, 0);
+        let recipient = new_account(Pubkey::new_unique(), program_id, false, true, 0);
+        let state = new_account(Pubkey::new_unique(), program_id, false, false, STATE_SIZE);
+        let verifier_key = Pubkey::new_unique();
+        let verifier = new_account(verifier_key, program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+
+        {
+            let mut state_data = state.try_borrow_mut_data().unwrap();
+            let gateway_state = state_mut_unchecked(&mut state_data).unwrap();
+            gateway_state.discriminator = STATE_DISCRIMINATOR;
+            gateway_state.zk_verifier = verifier_key;
+        }
+
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &empty_pay_authorized_data(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidSpentAuthorizationPda as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_already_spent_authorization() {
+        let program_id = Pubkey::new_unique();
+        let payer = new_account(Pubkey::new_unique(), program_id, true, true, 0);
+        let recipient = new_account(Pubkey::new_unique(), program_id, false, true, 0);
+        let state = new_account(Pubkey::new_unique(), program_id, false, false, STATE_SIZE);
+        let verifier_key = Pubkey::new_unique();
+        let verifier = new_account(verifier_key, program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+
+        {
+            let mut state_data = state.try_borrow_mut_data().unwrap();
+            let gateway_state = state_mut_unchecked(&mut state_data).unwrap();
+            gateway_state.discriminator = STATE_DISCRIMINATOR;
+            gateway_state.zk_verifier = verifier_key;
+        }
+
+        let auth_id = [7u8; 32];
+        let mut pay_data = empty_pay_authorized_data();
+        pay_data[0..32].copy_from_slice(&auth_id);
+
+        let (spent_pda, _bump) = Pubkey::find_program_address(&[b"spent", &auth_id], &program_id);
+        let spent = new_account(spent_pda, program_id, false, true, 0);
+
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &pay_data,
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::AuthorizationAlreadySpent as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_self_payment() {
+        let program_id = Pubkey::new_unique();
+        let payer_key = Pubkey::new_unique();
+        let payer = new_account(payer_key, program_id, true, true, 0);
+        let recipient = new_account(payer_key, program_id, false, true, 0);
+        let state = new_account(Pubkey::new_unique(), program_id, false, false, STATE_SIZE);
+        let verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &empty_pay_authorized_data(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidAccountAliasing as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_state_account_not_owned_by_program() {
+        let program_id = Pubkey::new_unique();
+        let payer = new_account(Pubkey::new_unique(), program_id, true, true, 0);
+        let recipient = new_account(Pubkey::new_unique(), program_id, false, true, 0);
+        let state = new_account(
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            false,
+            false,
+            STATE_SIZE,
+        );
+        let verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &empty_pay_authorized_data(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidAccountAliasing as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_state_account_aliased_with_payer() {
+        let program_id = Pubkey::new_unique();
+        let payer_key = Pubkey::new_unique();
+        let payer = new_account(payer_key, program_id, true, true, 0);
+        let recipient = new_account(Pubkey::new_unique(), program_id, false, true, 0);
+        let state = new_account(payer_key, program_id, false, false, STATE_SIZE);
+        let verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &empty_pay_authorized_data(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidAccountAliasing as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_state_account_aliased_with_recipient() {
+        let program_id = Pubkey::new_unique();
+        let payer = new_account(Pubkey::new_unique(), program_id, true, true, 0);
+        let recipient_key = Pubkey::new_unique();
+        let recipient = new_account(recipient_key, program_id, false, true, 0);
+        let state = new_account(recipient_key, program_id, false, false, STATE_SIZE);
+        let verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &empty_pay_authorized_data(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidAccountAliasing as u32)
+        );
+    }
+
+    #[test]
+    fn pay_authorized_rejects_verifier_aliased_with_system_program() {
+        let program_id = Pubkey::new_unique();
+        let payer = new_account(Pubkey::new_unique(), program_id, true, true, 0);
+        let recipient = new_account(Pubkey::new_unique(), program_id, false, true, 0);
+        let state = new_account(Pubkey::new_unique(), program_id, false, false, STATE_SIZE);
+        let verifier = new_account(system_program::id(), program_id, false, false, 0);
+        let system = new_account(system_program::id(), system_program::id(), false, false, 0);
+        let spent = new_account(Pubkey::new_unique(), system_program::id(), false, true, 0);
+        let err = process_pay_authorized(
+            &program_id,
+            &[payer, recipient, state, verifier, system, spent],
+            &empty_pay_authorized_data(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            err,
+            ProgramError::Custom(GatewayError::InvalidAccountAliasing as u32)
+        );
+    }
+
+    #[test]
+    fn verifier_account_metas_preserve_signer_and_writable_flags() {
+        let program_id = Pubkey::new_unique();
+        let input_account = new_account(Pubkey::new_unique(), program_id, false, true, 8);
+        let instructions_sysvar =
+            new_account(solana_program::sysvar::instructions::id(), program_id, false, false, 0);
+        let proof_pda = new_account(Pubkey::new_unique(), program_id, true, false, 0);
+        let extra = [&input_account, &instructions_sysvar, &proof_pda];
+
+        let metas = verifier_account_metas(&extra);
+
+        assert_eq!(metas.len(), 3);
+        assert_eq!(metas[0].pubkey, *input_account.key);
+        assert!(!metas[0].is_signer);
+        assert!(metas[0].is_writable);
+        assert_eq!(metas[1].pubkey, *instructions_sysvar.key);
+        assert!(!metas[1].is_signer);
+        assert!(!metas[1].is_writable);
+        assert_eq!(metas[2].pubkey, *proof_pda.key);
+        assert!(metas[2].is_signer);
+        assert!(!metas[2].is_writable);
+    }
+
+    #[test]
+    fn build_verifier_cpi_forwards_state_pda_and_verifier_program() {
+        let program_id = Pubkey::new_unique();
+        let state = new_account(Pubkey::new_unique(), program_id, false, true, STATE_SIZE);
+        let zk_verifier = new_account(Pubkey::new_unique(), program_id, false, false, 0);
+        let input_account = new_account(Pubkey::new_unique(), program_id, false, true, 8);
+        let extra = [&input_account];
+
+        let (metas, infos) = build_verifier_cpi(&state, &zk_verifier, &extra);
+
+        assert_eq!(metas.len(), 2);
+        assert_eq!(metas[0].pubkey, *state.key);
+        assert!(metas[0].is_signer);
+        assert_eq!(metas[0].is_writable, state.is_writable);
+        assert_eq!(metas[1].pubkey, *input_account.key);
+
+        assert_eq!(infos.len(), 3);
+        assert_eq!(*infos[0].key, *state.key);
+        assert_eq!(*infos[1].key, *input_account.key);
+        assert_eq!(*infos[2].key, *zk_verifier.key);
+    }
 }